@@ -6,10 +6,13 @@ use syn::punctuated::Punctuated;
 use syn::token::Comma;
 use syn::{
     parse_macro_input, Attribute, Data, DataStruct, DeriveInput, Field, Fields, FieldsNamed, Ident,
-    LitStr,
+    LitInt, LitStr, Path,
 };
 
-#[proc_macro_derive(SqlxCrud, attributes(database, table, external_id, id))]
+#[proc_macro_derive(
+    SqlxCrud,
+    attributes(database, table, external_id, id, foreign_key, queries, cache)
+)]
 pub fn derive(input: TokenStream) -> TokenStream {
     let DeriveInput {
         ident, data, attrs, ..
@@ -20,12 +23,27 @@ pub fn derive(input: TokenStream) -> TokenStream {
             ..
         }) => {
             let config = Config::new(&attrs, &ident, &named);
-            let static_model_schema = build_static_model_schema(&config);
+            let queries = SqlQueries::build(&config);
+
+            #[cfg(feature = "validate-sql")]
+            if let Err(compile_error) = validate_sql_queries(&config, &queries) {
+                return compile_error.into();
+            }
+
+            let static_model_schema = build_static_model_schema(&config, &queries);
             let sqlx_crud_impl = build_sqlx_crud_impl(&config);
+            let foreign_key_impls = build_foreign_key_impls(&config, &queries);
+            let named_queries_impl = build_named_queries_impl(&config);
+            let query_builder_impl = build_query_builder_impl(&config);
+            let update_changed_impl = build_update_changed_impl(&config);
 
             quote! {
                 #static_model_schema
                 #sqlx_crud_impl
+                #foreign_key_impls
+                #named_queries_impl
+                #query_builder_impl
+                #update_changed_impl
             }
             .into()
         }
@@ -33,7 +51,7 @@ pub fn derive(input: TokenStream) -> TokenStream {
     }
 }
 
-fn build_static_model_schema(config: &Config) -> TokenStream2 {
+fn build_static_model_schema(config: &Config, queries: &SqlQueries) -> TokenStream2 {
     let crate_name = &config.crate_name;
     let model_schema_ident = &config.model_schema_ident;
     let table_name = &config.table_name;
@@ -46,7 +64,14 @@ fn build_static_model_schema(config: &Config) -> TokenStream2 {
         .flat_map(|f| &f.ident)
         .map(|f| LitStr::new(format!("{}", f).as_str(), f.span()));
 
-    let sql_queries = build_sql_queries(config);
+    let select_sql = &queries.select_sql;
+    let select_by_id_sql = &queries.select_by_id_sql;
+    let insert_sql = &queries.insert_sql;
+    let update_by_id_sql = &queries.update_by_id_sql;
+    let delete_by_id_sql = &queries.delete_by_id_sql;
+    let paginated_sql = &queries.paginated_sql;
+    let create_table_sql = &queries.create_table_sql;
+    let drop_table_sql = &queries.drop_table_sql;
 
     quote! {
         #[automatically_derived]
@@ -54,87 +79,344 @@ fn build_static_model_schema(config: &Config) -> TokenStream2 {
             table_name: #table_name,
             id_column: #id_column,
             columns: [#(#columns),*],
-            #sql_queries
+            select_sql: #select_sql,
+            select_by_id_sql: #select_by_id_sql,
+            insert_sql: #insert_sql,
+            update_by_id_sql: #update_by_id_sql,
+            delete_by_id_sql: #delete_by_id_sql,
+            paginated_sql: #paginated_sql,
+            create_table_sql: #create_table_sql,
+            drop_table_sql: #drop_table_sql,
         };
     }
 }
 
-fn build_sql_queries(config: &Config) -> TokenStream2 {
-    let table_name = config.quote_ident(&config.table_name);
-    let id_column = format!(
-        "{}.{}",
-        &table_name,
-        config.quote_ident(&config.id_column_ident.to_string())
-    );
+/// All SQL statements generated for a single derived struct. Built once per
+/// `derive(SqlxCrud)` invocation so both the `Metadata` literal and (under the
+/// `validate-sql` feature) the compile-time parser check work from the same
+/// strings.
+struct SqlQueries {
+    select_sql: String,
+    select_by_id_sql: String,
+    insert_sql: String,
+    update_by_id_sql: String,
+    delete_by_id_sql: String,
+    paginated_sql: String,
+    create_table_sql: String,
+    drop_table_sql: String,
+}
+
+impl SqlQueries {
+    fn build(config: &Config) -> Self {
+        let table_name = config.quote_ident(&config.table_name);
+        let id_column = format!(
+            "{}.{}",
+            &table_name,
+            config.quote_ident(&config.id_column_ident.to_string())
+        );
+
+        let insert_bind_cnt = if config.external_id {
+            config.named.iter().count()
+        } else {
+            config.named.iter().count() - 1
+        };
+        let insert_sql_binds = (0..insert_bind_cnt)
+            .map(|i| format!("${}", i + 1))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let update_sql_binds = config
+            .named
+            .iter()
+            .flat_map(|f| &f.ident)
+            .filter(|i| *i != &config.id_column_ident)
+            .enumerate()
+            .map(|(i, ident)| format!("{} = ${}", config.quote_ident(&ident.to_string()), i + 1))
+            .collect::<Vec<_>>();
+
+        let insert_column_list = config
+            .named
+            .iter()
+            .flat_map(|f| &f.ident)
+            .filter(|i| config.external_id || *i != &config.id_column_ident)
+            .map(|i| config.quote_ident(&i.to_string()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let column_list = config
+            .named
+            .iter()
+            .flat_map(|f| &f.ident)
+            .map(|i| format!("{}.{}", &table_name, config.quote_ident(&i.to_string())))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let select_sql = format!("SELECT {} FROM {}", column_list, table_name);
+        let paginated_sql = format!(
+            "SELECT {} FROM {} LIMIT $1 OFFSET $2",
+            column_list, table_name
+        );
+        let select_by_id_sql = format!(
+            "SELECT {} FROM {} WHERE {} = $1 LIMIT 1",
+            column_list, table_name, id_column
+        );
+        let insert_sql = format!(
+            "INSERT INTO {} ({}) VALUES ({}) RETURNING {}",
+            table_name, insert_column_list, insert_sql_binds, column_list
+        );
+        let update_by_id_sql = format!(
+            "UPDATE {} SET {} WHERE {} = ${} RETURNING {}",
+            table_name,
+            update_sql_binds.join(", "),
+            id_column,
+            update_sql_binds.len() + 1,
+            column_list
+        );
+        let delete_by_id_sql = format!("DELETE FROM {} WHERE {} = $1", table_name, id_column);
+        let create_table_sql = build_create_table_sql(config, &table_name);
+        let drop_table_sql = format!("DROP TABLE {}", table_name);
+
+        Self {
+            select_sql,
+            select_by_id_sql,
+            insert_sql,
+            update_by_id_sql,
+            delete_by_id_sql,
+            paginated_sql,
+            create_table_sql,
+            drop_table_sql,
+        }
+    }
+
+    /// Pairs each generated statement with the `Metadata` field it backs, for
+    /// diagnostics that need to name the offending statement. Only used by
+    /// [`validate_sql_queries`], which is itself feature-gated.
+    #[cfg(feature = "validate-sql")]
+    fn iter(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        [
+            ("select_sql", self.select_sql.as_str()),
+            ("select_by_id_sql", self.select_by_id_sql.as_str()),
+            ("insert_sql", self.insert_sql.as_str()),
+            ("update_by_id_sql", self.update_by_id_sql.as_str()),
+            ("delete_by_id_sql", self.delete_by_id_sql.as_str()),
+            ("paginated_sql", self.paginated_sql.as_str()),
+            ("create_table_sql", self.create_table_sql.as_str()),
+            ("drop_table_sql", self.drop_table_sql.as_str()),
+        ]
+        .into_iter()
+    }
+}
 
-    let insert_bind_cnt = if config.external_id {
-        config.named.iter().count()
-    } else {
-        config.named.iter().count() - 1
+/// Parses every generated statement with [`sqlparser`] under the dialect that
+/// matches `#[database(...)]`, so a malformed table/column name fails the
+/// build instead of the first query at runtime. Bind placeholders (`$1`, `?`)
+/// aren't valid standalone SQL, so they're swapped for a harmless literal
+/// before parsing; only the surrounding statement shape is being checked.
+///
+/// Every generated statement is built from the table name (`#[table(...)]`
+/// or its default) plus each field's column name, so a parse failure is
+/// localized by matching the offending identifier out of the parser's error
+/// message against those known names and spanning the diagnostic on whichever
+/// attribute or field produced it, falling back to the struct ident only when
+/// no candidate matches.
+#[cfg(feature = "validate-sql")]
+fn validate_sql_queries(config: &Config, queries: &SqlQueries) -> Result<(), TokenStream2> {
+    use sqlparser::dialect::{
+        GenericDialect, MsSqlDialect, MySqlDialect, PostgreSqlDialect, SQLiteDialect,
     };
-    let insert_sql_binds = (0..insert_bind_cnt)
-        .map(|i| format!("${}", i + 1))
-        .collect::<Vec<_>>()
-        .join(", ");
+    use sqlparser::parser::Parser;
 
-    let update_sql_binds = config
-        .named
-        .iter()
-        .flat_map(|f| &f.ident)
-        .filter(|i| *i != &config.id_column_ident)
-        .enumerate()
-        .map(|(i, ident)| format!("{} = ${}", config.quote_ident(&ident.to_string()), i + 1))
-        .collect::<Vec<_>>();
+    let dialect: Box<dyn sqlparser::dialect::Dialect> = match config.db_ty {
+        DbType::Any => Box::new(GenericDialect {}),
+        DbType::Mssql => Box::new(MsSqlDialect {}),
+        DbType::MySql => Box::new(MySqlDialect {}),
+        DbType::Postgres => Box::new(PostgreSqlDialect {}),
+        DbType::Sqlite => Box::new(SQLiteDialect {}),
+    };
 
-    let insert_column_list = config
+    // (name, span) candidates an error message is matched against, longest
+    // name first so e.g. a struct's `id` field doesn't shadow a longer match.
+    let mut candidates: Vec<(String, proc_macro2::Span)> = config
         .named
         .iter()
-        .flat_map(|f| &f.ident)
-        .filter(|i| config.external_id || *i != &config.id_column_ident)
-        .map(|i| config.quote_ident(&i.to_string()))
-        .collect::<Vec<_>>()
-        .join(", ");
-    let column_list = config
-        .named
+        .flat_map(|f| f.ident.as_ref())
+        .map(|i| (i.to_string(), i.span()))
+        .collect();
+    candidates.push((config.table_name.clone(), config.table_name_span));
+    candidates.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+
+    for (name, sql) in queries.iter() {
+        let parsable = strip_bind_placeholders(sql);
+        if let Err(err) = Parser::parse_sql(dialect.as_ref(), &parsable) {
+            let err_text = err.to_string();
+            let span = find_candidate_span(&err_text, &candidates).unwrap_or_else(|| config.ident.span());
+            return Err(syn::Error::new(
+                span,
+                format!("sqlx-crud generated an invalid `{}`: {}\n  {}", name, err, sql),
+            )
+            .to_compile_error());
+        }
+    }
+    Ok(())
+}
+
+/// Picks the span of whichever `candidates` entry is named as a whole word in
+/// `err_text`, e.g. matching `` `id` `` in `"Expected: identifier, found: id"`
+/// without the `"identifier"` substring itself falsely matching an `id`
+/// candidate.
+#[cfg(feature = "validate-sql")]
+fn find_candidate_span(
+    err_text: &str,
+    candidates: &[(String, proc_macro2::Span)],
+) -> Option<proc_macro2::Span> {
+    let tokens: Vec<&str> = err_text
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|t| !t.is_empty())
+        .collect();
+    candidates
         .iter()
-        .flat_map(|f| &f.ident)
-        .map(|i| format!("{}.{}", &table_name, config.quote_ident(&i.to_string())))
-        .collect::<Vec<_>>()
-        .join(", ");
+        .find(|(candidate, _)| tokens.contains(&candidate.as_str()))
+        .map(|(_, span)| *span)
+}
 
-    let select_sql = format!("SELECT {} FROM {}", column_list, table_name);
-    let paginated_sql = format!(
-        "SELECT {} FROM {} LIMIT $1 OFFSET $2",
-        column_list, table_name
-    );
-    let select_by_id_sql = format!(
-        "SELECT {} FROM {} WHERE {} = $1 LIMIT 1",
-        column_list, table_name, id_column
-    );
-    let insert_sql = format!(
-        "INSERT INTO {} ({}) VALUES ({}) RETURNING {}",
-        table_name, insert_column_list, insert_sql_binds, column_list
-    );
-    let update_by_id_sql = format!(
-        "UPDATE {} SET {} WHERE {} = ${} RETURNING {}",
-        table_name,
-        update_sql_binds.join(", "),
-        id_column,
-        update_sql_binds.len() + 1,
-        column_list
-    );
-    let delete_by_id_sql = format!("DELETE FROM {} WHERE {} = $1", table_name, id_column);
+#[cfg(feature = "validate-sql")]
+fn strip_bind_placeholders(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '$' => {
+                while matches!(chars.peek(), Some(d) if d.is_ascii_digit()) {
+                    chars.next();
+                }
+                out.push('0');
+            }
+            '?' => out.push('0'),
+            _ => out.push(c),
+        }
+    }
+    out
+}
 
-    quote! {
-        select_sql: #select_sql,
-        select_by_id_sql: #select_by_id_sql,
-        insert_sql: #insert_sql,
-        update_by_id_sql: #update_by_id_sql,
-        delete_by_id_sql: #delete_by_id_sql,
-        paginated_sql: #paginated_sql,
+/// Assembles a `CREATE TABLE` statement from the struct's fields, mapping each
+/// field's Rust type to a backend-appropriate SQL column type (see
+/// [`column_sql_type`]). The id column becomes the table's `PRIMARY KEY`; when
+/// the id isn't `#[external_id]`, an auto-incrementing clause is emitted
+/// instead of relying on the caller to supply one. Under `#[database(Any)]`
+/// the concrete backend is only known at runtime, so no auto-increment clause
+/// can be chosen ahead of time and the id column falls back to a plain
+/// `PRIMARY KEY (...)` constraint; callers targeting `Any` who need
+/// auto-increment should supply their own DDL instead of `create_table_sql()`.
+fn build_create_table_sql(config: &Config, quoted_table: &str) -> String {
+    let mut column_defs = Vec::new();
+    let mut id_is_inline_pk = false;
+
+    for field in config.named.iter() {
+        let ident = field.ident.as_ref().expect("named field");
+        let column_name = config.quote_ident(&ident.to_string());
+        let is_id = ident == &config.id_column_ident;
+        let (sql_type, nullable) = column_sql_type(&field.ty, &config.db_ty);
+
+        let mut def = format!("{} {}", column_name, sql_type);
+        if is_id && !config.external_id {
+            match config.db_ty {
+                // SQLite's AUTOINCREMENT is only valid on an inline `INTEGER PRIMARY KEY` column.
+                DbType::Sqlite => {
+                    def = format!("{} INTEGER PRIMARY KEY AUTOINCREMENT", column_name);
+                    id_is_inline_pk = true;
+                }
+                DbType::Postgres => def.push_str(" GENERATED ALWAYS AS IDENTITY"),
+                DbType::MySql => def.push_str(" AUTO_INCREMENT"),
+                DbType::Mssql => def.push_str(" IDENTITY(1,1)"),
+                // The real backend behind `Any` is only known at runtime, so no
+                // dialect-specific auto-increment syntax can be baked in here;
+                // the id column still gets a plain `PRIMARY KEY (...)` below.
+                DbType::Any => {}
+            }
+        } else if !nullable {
+            def.push_str(" NOT NULL");
+        }
+        column_defs.push(def);
+    }
+
+    if !id_is_inline_pk {
+        let id_column = config.quote_ident(&config.id_column_ident.to_string());
+        column_defs.push(format!("PRIMARY KEY ({})", id_column));
+    }
+
+    format!("CREATE TABLE {} ({})", quoted_table, column_defs.join(", "))
+}
+
+/// Maps a field's Rust type to a `(sql_type, nullable)` pair for the given
+/// backend, mirroring what a `SqlColumnType<DbType>` trait impl would return
+/// for that type. `Option<T>` unwraps to `T`'s column type with `nullable`
+/// set; everything else is `NOT NULL`.
+fn column_sql_type(ty: &syn::Type, db_ty: &DbType) -> (String, bool) {
+    match option_inner_type(ty) {
+        Some(inner) => (scalar_sql_type(inner, db_ty), true),
+        None => (scalar_sql_type(ty, db_ty), false),
     }
 }
 
+fn option_inner_type(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    args.args.iter().find_map(|arg| match arg {
+        syn::GenericArgument::Type(inner) => Some(inner),
+        _ => None,
+    })
+}
+
+fn scalar_sql_type(ty: &syn::Type, db_ty: &DbType) -> String {
+    let type_name = match ty {
+        syn::Type::Path(type_path) => type_path.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    };
+
+    match type_name.as_deref() {
+        Some("i8" | "i16" | "i32" | "u8" | "u16" | "u32") => match db_ty {
+            DbType::MySql => "INT",
+            _ => "INTEGER",
+        },
+        Some("i64" | "u64" | "isize" | "usize") => "BIGINT",
+        Some("f32" | "f64") => match db_ty {
+            DbType::Postgres => "DOUBLE PRECISION",
+            DbType::Mssql => "FLOAT",
+            _ => "REAL",
+        },
+        Some("bool") => match db_ty {
+            DbType::MySql => "TINYINT(1)",
+            DbType::Mssql => "BIT",
+            _ => "BOOLEAN",
+        },
+        Some("String" | "str") => match db_ty {
+            DbType::MySql => "VARCHAR(255)",
+            DbType::Mssql => "NVARCHAR(255)",
+            _ => "TEXT",
+        },
+        #[cfg(feature = "chrono")]
+        Some("NaiveDateTime" | "DateTime") => match db_ty {
+            DbType::Postgres => "TIMESTAMPTZ",
+            DbType::Mssql => "DATETIME2",
+            _ => "DATETIME",
+        },
+        #[cfg(feature = "uuid")]
+        Some("Uuid") => match db_ty {
+            DbType::Postgres => "UUID",
+            _ => "CHAR(36)",
+        },
+        _ => "TEXT",
+    }
+    .to_string()
+}
+
 fn build_sqlx_crud_impl(config: &Config) -> TokenStream2 {
     let crate_name = &config.crate_name;
     let ident = &config.ident;
@@ -178,6 +460,13 @@ fn build_sqlx_crud_impl(config: &Config) -> TokenStream2 {
         .flat_map(|f| &f.ident)
         .map(|i| quote! { ::sqlx::encode::Encode::<#db_ty>::size_hint(&self.#i) });
 
+    let cache_support = config
+        .cache
+        .as_ref()
+        .map(|cache| build_cache_support(config, cache, id_ty, &db_ty));
+    let cache_overrides = cache_support.as_ref().map(|c| &c.trait_method_overrides);
+    let cache_static_and_clear = cache_support.as_ref().map(|c| &c.static_and_clear);
+
     quote! {
         #[automatically_derived]
         impl #crate_name::traits::Schema for #ident {
@@ -222,6 +511,14 @@ fn build_sqlx_crud_impl(config: &Config) -> TokenStream2 {
             fn paginated_sql() -> &'static str {
                 #model_schema_ident.paginated_sql
             }
+
+            fn create_table_sql() -> &'static str {
+                #model_schema_ident.create_table_sql
+            }
+
+            fn drop_table_sql() -> &'static str {
+                #model_schema_ident.drop_table_sql
+            }
         }
 
         #[automatically_derived]
@@ -254,6 +551,575 @@ fn build_sqlx_crud_impl(config: &Config) -> TokenStream2 {
                 let _ = args.add(offset);
                 args
             }
+
+            #cache_overrides
+        }
+
+        #cache_static_and_clear
+    }
+}
+
+struct CacheSupport {
+    /// `static` row cache plus the inherent `clear_cache()` method.
+    static_and_clear: TokenStream2,
+    /// Overrides for `Crud::by_id`/`update`/`delete` that read through and
+    /// invalidate the cache above.
+    trait_method_overrides: TokenStream2,
+}
+
+/// Builds the `#[cache]` support for a single derived struct: a process-wide
+/// `Id -> Self` map behind an `RwLock`, a `clear_cache()` escape hatch, and
+/// overrides of the `Crud` trait's `by_id`/`update`/`delete` default methods
+/// that read through the map on hit and invalidate it on write. Requires
+/// `Self: Clone`, same as the cached row itself.
+fn build_cache_support(
+    config: &Config,
+    cache: &CacheConfig,
+    id_ty: &syn::Type,
+    db_ty: &TokenStream2,
+) -> CacheSupport {
+    let crate_name = &config.crate_name;
+    let ident = &config.ident;
+    let cache_ident = format_ident!("{}_ID_CACHE", ident.to_string().to_screaming_snake_case());
+    let capacity_check = match cache.capacity {
+        Some(capacity) => quote! { cache.len() < #capacity },
+        None => quote! { true },
+    };
+
+    let static_and_clear = quote! {
+        #[automatically_derived]
+        #[doc(hidden)]
+        static #cache_ident: ::std::sync::OnceLock<::std::sync::RwLock<::std::collections::HashMap<#id_ty, #ident>>> =
+            ::std::sync::OnceLock::new();
+
+        #[automatically_derived]
+        impl #ident {
+            fn cache() -> &'static ::std::sync::RwLock<::std::collections::HashMap<#id_ty, #ident>> {
+                #cache_ident.get_or_init(Default::default)
+            }
+
+            /// Evicts every row cached for this type by `#[cache]`.
+            pub fn clear_cache() {
+                Self::cache().write().unwrap().clear();
+            }
+        }
+    };
+
+    let trait_method_overrides = quote! {
+        async fn by_id(
+            executor: &'e ::sqlx::pool::Pool<#db_ty>,
+            id: Self::Id,
+        ) -> ::sqlx::Result<Option<Self>> {
+            if let Some(row) = Self::cache().read().unwrap().get(&id).cloned() {
+                return Ok(Some(row));
+            }
+
+            let row = ::sqlx::query_as::<_, Self>(
+                <Self as #crate_name::traits::Schema>::select_by_id_sql(),
+            )
+            .bind(id)
+            .fetch_optional(executor)
+            .await?;
+
+            if let Some(row) = &row {
+                let mut cache = Self::cache().write().unwrap();
+                if #capacity_check {
+                    cache.insert(id, row.clone());
+                }
+            }
+            Ok(row)
+        }
+
+        async fn update(self, executor: &'e ::sqlx::pool::Pool<#db_ty>) -> ::sqlx::Result<Self> {
+            use #crate_name::traits::Crud as _;
+            let args = self.update_args();
+            let row = ::sqlx::query_as_with::<_, Self, _>(
+                <Self as #crate_name::traits::Schema>::update_by_id_sql(),
+                args,
+            )
+            .fetch_one(executor)
+            .await?;
+
+            let id = #crate_name::traits::Schema::id(&row);
+            let mut cache = Self::cache().write().unwrap();
+            if #capacity_check {
+                cache.insert(id, row.clone());
+            } else {
+                cache.remove(&id);
+            }
+            Ok(row)
+        }
+
+        async fn delete(
+            executor: &'e ::sqlx::pool::Pool<#db_ty>,
+            id: Self::Id,
+        ) -> ::sqlx::Result<u64> {
+            let result = ::sqlx::query(<Self as #crate_name::traits::Schema>::delete_by_id_sql())
+                .bind(id)
+                .execute(executor)
+                .await?;
+            Self::cache().write().unwrap().remove(&id);
+            Ok(result.rows_affected())
+        }
+    };
+
+    CacheSupport {
+        static_and_clear,
+        trait_method_overrides,
+    }
+}
+
+/// Generates the `query()` entry point into a composable
+/// `traits::QueryBuilder`, seeded with this struct's own table name and
+/// column list so `.filter(col, Op::Gt, val)` can only reference real
+/// columns. The builder itself (bind-index tracking, `Op` variants, the
+/// Postgres-only `Contains`/`ContainedBy` operators) lives in `traits`
+/// alongside `Schema`/`Crud`, since it's identical for every derived struct.
+fn build_query_builder_impl(config: &Config) -> TokenStream2 {
+    let crate_name = &config.crate_name;
+    let ident = &config.ident;
+    let db_ty = config.db_ty.sqlx_db();
+
+    quote! {
+        #[automatically_derived]
+        impl #ident {
+            pub fn query<'q>() -> #crate_name::traits::QueryBuilder<'q, #db_ty, Self> {
+                #crate_name::traits::QueryBuilder::new(
+                    <Self as #crate_name::traits::Schema>::table_name(),
+                    <Self as #crate_name::traits::Schema>::columns(),
+                )
+            }
+        }
+    }
+}
+
+/// Generates `update_changed_sql`/`update_changed_args`: an `UPDATE` that
+/// always sets the struct's non-`Option` columns but only sets an `Option<T>`
+/// column when `self`'s value for it is `Some(_)`, so `None` means "leave
+/// unchanged" rather than "set to NULL" (unlike `update_by_id_sql`). The two
+/// methods must be called against the same, unmutated `self` — `_sql` reads
+/// which columns are `Some` to number the placeholders, and `_args` (which
+/// consumes `self`) must walk the same fields in the same order to match.
+fn build_update_changed_impl(config: &Config) -> TokenStream2 {
+    let ident = &config.ident;
+    let db_ty = config.db_ty.sqlx_db();
+    let id_column_ident = &config.id_column_ident;
+
+    let table_name = config.quote_ident(&config.table_name);
+    let id_column_unqualified = config.quote_ident(&config.id_column_ident.to_string());
+    let id_column = format!("{}.{}", &table_name, &id_column_unqualified);
+    let column_list = config
+        .named
+        .iter()
+        .flat_map(|f| &f.ident)
+        .map(|i| format!("{}.{}", &table_name, config.quote_ident(&i.to_string())))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let is_settable_field = |f: &&Field| f.ident.as_ref() != Some(id_column_ident);
+
+    let always_set_fields: Vec<Ident> = config
+        .named
+        .iter()
+        .filter(is_settable_field)
+        .filter(|f| option_inner_type(&f.ty).is_none())
+        .map(|f| f.ident.clone().expect("named field"))
+        .collect();
+    let conditional_fields: Vec<Ident> = config
+        .named
+        .iter()
+        .filter(is_settable_field)
+        .filter(|f| option_inner_type(&f.ty).is_some())
+        .map(|f| f.ident.clone().expect("named field"))
+        .collect();
+
+    let always_set_columns: Vec<String> = always_set_fields
+        .iter()
+        .map(|i| config.quote_ident(&i.to_string()))
+        .collect();
+    let conditional_columns: Vec<String> = conditional_fields
+        .iter()
+        .map(|i| config.quote_ident(&i.to_string()))
+        .collect();
+
+    quote! {
+        #[automatically_derived]
+        impl #ident {
+            /// The `UPDATE` statement for [`Self::update_changed_args`], with
+            /// placeholders numbered for only the columns `self` will set.
+            pub fn update_changed_sql(&self) -> String {
+                let mut set_fragments: Vec<String> = Vec::new();
+                let mut bind_index = 1usize;
+
+                #(
+                    set_fragments.push(format!("{} = ${}", #always_set_columns, bind_index));
+                    bind_index += 1;
+                )*
+
+                #(
+                    if self.#conditional_fields.is_some() {
+                        set_fragments.push(format!("{} = ${}", #conditional_columns, bind_index));
+                        bind_index += 1;
+                    }
+                )*
+
+                // Every settable column is `Option` and `self` has none of them
+                // `Some` — fall back to a no-op self-assignment so the `SET`
+                // clause stays valid SQL instead of coming out empty.
+                if set_fragments.is_empty() {
+                    set_fragments.push(format!("{col} = {col}", col = #id_column_unqualified));
+                }
+
+                format!(
+                    "UPDATE {} SET {} WHERE {} = ${} RETURNING {}",
+                    #table_name,
+                    set_fragments.join(", "),
+                    #id_column,
+                    bind_index,
+                    #column_list,
+                )
+            }
+
+            /// The bound arguments for [`Self::update_changed_sql`]: every
+            /// non-`Option` field, then every `Option` field that is `Some(_)`
+            /// on `self`, then the id — the same order `update_changed_sql`
+            /// numbered its placeholders in.
+            pub fn update_changed_args<'e>(
+                self,
+            ) -> <#db_ty as ::sqlx::database::Database>::Arguments<'e> {
+                use ::sqlx::Arguments as _;
+                let mut args = <#db_ty as ::sqlx::database::Database>::Arguments::default();
+
+                #(
+                    let _ = args.add(self.#always_set_fields);
+                )*
+                #(
+                    if let Some(value) = self.#conditional_fields {
+                        let _ = args.add(value);
+                    }
+                )*
+                let _ = args.add(self.#id_column_ident);
+
+                args
+            }
+        }
+    }
+}
+
+/// A `#[foreign_key(Parent, column = "id")]` field attribute, recording that
+/// this field holds a value referencing `column` on `Parent`'s table. An
+/// optional `table = "..."` overrides the referenced table name for a
+/// `Parent` that doesn't use its default (e.g. it has its own
+/// `#[table(...)]`), since this macro invocation can't see `Parent`'s
+/// attributes.
+struct ForeignKey {
+    /// The local field holding the foreign key value.
+    field_ident: Ident,
+    /// `Parent` in `#[foreign_key(Parent, ...)]`, i.e. the referenced `Schema`.
+    parent_ty: Path,
+    /// The referenced column on `Parent`'s table; defaults to `id`.
+    parent_column: String,
+    /// `Parent`'s table name; defaults to `Parent`'s type name in table case.
+    /// Macro expansion has no visibility into `Parent`'s own `#[table(...)]`
+    /// attribute, so this must be supplied explicitly when `Parent` doesn't
+    /// use its default table name.
+    parent_table: Option<String>,
+    /// The relation name used in `select_with_<rel>_sql`/`fetch_<rel>`,
+    /// derived from the field name with a trailing `_id` stripped.
+    rel_name: String,
+}
+
+impl ForeignKey {
+    fn parse_all(named: &Punctuated<Field, Comma>) -> Vec<Self> {
+        named
+            .iter()
+            .filter_map(|field| {
+                let attr = field
+                    .attrs
+                    .iter()
+                    .find(|a| a.path().is_ident("foreign_key"))?;
+                Some(Self::parse(field, attr))
+            })
+            .collect()
+    }
+
+    fn parse(field: &Field, attr: &Attribute) -> Self {
+        let field_ident = field.ident.clone().expect("named field");
+
+        let mut parent_ty = None;
+        let mut parent_column = "id".to_string();
+        let mut parent_table = None;
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("column") {
+                parent_column = meta.value()?.parse::<LitStr>()?.value();
+            } else if meta.path.is_ident("table") {
+                parent_table = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else {
+                parent_ty = Some(meta.path.clone());
+            }
+            Ok(())
+        })
+        .expect("valid #[foreign_key(Parent, column = \"...\", table = \"...\")] attribute");
+        let parent_ty = parent_ty.expect("#[foreign_key] requires a referenced type");
+
+        let rel_name = field_ident
+            .to_string()
+            .strip_suffix("_id")
+            .unwrap_or(&field_ident.to_string())
+            .to_string();
+
+        Self {
+            field_ident,
+            parent_ty,
+            parent_column,
+            parent_table,
+            rel_name,
+        }
+    }
+}
+
+/// The `select_with_<rel>_sql`/`fetch_<rel>_sql` pair for one `#[foreign_key]`
+/// field, given the already-quoted table/column names involved. Split out
+/// from [`build_foreign_key_impls`] so the string-building is testable
+/// without going through the derive macro.
+fn foreign_key_sql(
+    select_sql: &str,
+    table_name: &str,
+    local_column: &str,
+    parent_table: &str,
+    parent_column: &str,
+) -> (String, String) {
+    let select_with_rel_sql = format!(
+        "{} JOIN {} ON {}.{} = {}.{}",
+        select_sql, parent_table, table_name, local_column, parent_table, parent_column
+    );
+    // `Parent::select_by_id_sql()` always filters on `Parent`'s own id
+    // column, so it can't be reused here when `parent_column` names a
+    // different column; build a dedicated statement against it instead.
+    let fetch_rel_sql = format!("SELECT * FROM {} WHERE {} = $1", parent_table, parent_column);
+    (select_with_rel_sql, fetch_rel_sql)
+}
+
+/// For each `#[foreign_key]` field, generates a `select_with_<rel>_sql()`
+/// (this table's own `select_sql` plus a `JOIN` on the referenced table) and a
+/// `fetch_<rel>` helper that loads the related row by `parent_column`.
+fn build_foreign_key_impls(config: &Config, queries: &SqlQueries) -> TokenStream2 {
+    let crate_name = &config.crate_name;
+    let ident = &config.ident;
+    let db_ty = config.db_ty.sqlx_db();
+    let table_name = config.quote_ident(&config.table_name);
+    let select_sql = &queries.select_sql;
+
+    let impls = config.foreign_keys.iter().map(|fk| {
+        let select_with_rel_sql_fn = format_ident!("select_with_{}_sql", fk.rel_name);
+        let fetch_rel_fn = format_ident!("fetch_{}", fk.rel_name);
+        let field_ident = &fk.field_ident;
+        let parent_ty = &fk.parent_ty;
+        let parent_table = config.quote_ident(&fk.parent_table.clone().unwrap_or_else(|| {
+            fk.parent_ty
+                .segments
+                .last()
+                .expect("referenced type")
+                .ident
+                .to_string()
+                .to_table_case()
+        }));
+        let local_column = config.quote_ident(&field_ident.to_string());
+        let parent_column = config.quote_ident(&fk.parent_column);
+
+        let (select_with_rel_sql, fetch_rel_sql) =
+            foreign_key_sql(select_sql, &table_name, &local_column, &parent_table, &parent_column);
+
+        quote! {
+            #[automatically_derived]
+            impl #ident {
+                #[doc = concat!(
+                    "The generated SQL for fetching `", stringify!(#ident),
+                    "` rows joined to their referenced `", stringify!(#parent_ty), "`."
+                )]
+                pub fn #select_with_rel_sql_fn() -> &'static str {
+                    #select_with_rel_sql
+                }
+
+                #[doc = concat!(
+                    "Fetches the `", stringify!(#parent_ty),
+                    "` referenced by this row's `", stringify!(#field_ident), "`."
+                )]
+                pub async fn #fetch_rel_fn<'e>(
+                    &self,
+                    executor: &'e ::sqlx::pool::Pool<#db_ty>,
+                ) -> ::sqlx::Result<#parent_ty>
+                where
+                    #parent_ty: #crate_name::traits::Schema
+                        + for<'r> ::sqlx::FromRow<'r, <#db_ty as ::sqlx::Database>::Row>
+                        + Send
+                        + Unpin,
+                {
+                    ::sqlx::query_as::<_, #parent_ty>(#fetch_rel_sql)
+                        .bind(self.#field_ident)
+                        .fetch_one(executor)
+                        .await
+                }
+            }
+        }
+    });
+
+    quote! { #(#impls)* }
+}
+
+/// One `-- name: <name>` block parsed out of a `#[queries("...")]` file.
+struct NamedQuery {
+    name: String,
+    /// Comment lines immediately following the `-- name:` marker, used as the
+    /// generated accessor's doc comment.
+    doc: Vec<String>,
+    /// SQL text with `:param` placeholders rewritten to the backend's
+    /// positional bind syntax.
+    sql: String,
+    /// Parameter names in bind order, for building the `Arguments` by hand.
+    params: Vec<String>,
+}
+
+/// Parses a Yesql-style file of `-- name: find_active` annotated statements
+/// into one [`NamedQuery`] per block.
+fn parse_named_queries(contents: &str, db_ty: &DbType) -> Vec<NamedQuery> {
+    let mut queries = Vec::new();
+    let mut current: Option<(String, Vec<String>, Vec<&str>)> = None;
+
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if let Some(name) = trimmed.strip_prefix("-- name:") {
+            if let Some((name, doc, sql_lines)) = current.take() {
+                queries.push(finish_named_query(name, doc, sql_lines, db_ty));
+            }
+            current = Some((name.trim().to_string(), Vec::new(), Vec::new()));
+            continue;
+        }
+
+        let Some((_, doc, sql_lines)) = current.as_mut() else {
+            continue;
+        };
+        if sql_lines.is_empty() {
+            if let Some(comment) = trimmed.strip_prefix("--") {
+                doc.push(comment.trim().to_string());
+                continue;
+            }
+            if trimmed.is_empty() {
+                continue;
+            }
+        }
+        sql_lines.push(line);
+    }
+    if let Some((name, doc, sql_lines)) = current.take() {
+        queries.push(finish_named_query(name, doc, sql_lines, db_ty));
+    }
+
+    queries
+}
+
+fn finish_named_query(
+    name: String,
+    doc: Vec<String>,
+    sql_lines: Vec<&str>,
+    db_ty: &DbType,
+) -> NamedQuery {
+    let raw_sql = sql_lines.join("\n").trim().to_string();
+    let (sql, params) = rewrite_named_placeholders(&raw_sql, db_ty);
+    NamedQuery {
+        name,
+        doc,
+        sql,
+        params,
+    }
+}
+
+/// Rewrites Yesql-style `:param` placeholders into the backend's positional
+/// bind syntax (`$n` for Postgres, `?` elsewhere), returning the rewritten SQL
+/// alongside the parameter names in bind order.
+fn rewrite_named_placeholders(sql: &str, db_ty: &DbType) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(sql.len());
+    let mut params = Vec::new();
+    let mut chars = sql.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        // A Postgres-style `::` type cast, not the start of a `:name` bind.
+        if c == ':' && chars.peek() == Some(&':') {
+            out.push(c);
+            out.push(chars.next().expect("peeked"));
+            continue;
+        }
+
+        let starts_param = c == ':' && matches!(chars.peek(), Some(n) if n.is_alphabetic() || *n == '_');
+        if !starts_param {
+            out.push(c);
+            continue;
+        }
+
+        let mut name = String::new();
+        while matches!(chars.peek(), Some(n) if n.is_alphanumeric() || *n == '_') {
+            name.push(chars.next().expect("peeked"));
+        }
+        params.push(name);
+        match db_ty {
+            DbType::Postgres => out.push_str(&format!("${}", params.len())),
+            DbType::Any | DbType::Mssql | DbType::MySql | DbType::Sqlite => out.push('?'),
+        }
+    }
+
+    (out, params)
+}
+
+/// Generates one `&'static str` accessor (and its bind-order parameter list)
+/// per `-- name:` block in the file named by `#[queries("...")]`, plus an
+/// `include_str!` of that file so edits to it trigger a recompile.
+fn build_named_queries_impl(config: &Config) -> TokenStream2 {
+    let Some(path) = &config.queries_file else {
+        return quote! {};
+    };
+
+    let ident = &config.ident;
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set");
+    let full_path = std::path::Path::new(&manifest_dir).join(path);
+    let contents = std::fs::read_to_string(&full_path)
+        .unwrap_or_else(|err| panic!("#[queries(\"{}\")]: {}", path, err));
+    // `include_str!` resolves relative to this source file, not the crate
+    // root, so hand it the absolute path we already read from instead of the
+    // user-provided `path`.
+    let full_path_str = full_path.to_string_lossy().into_owned();
+
+    let accessors = parse_named_queries(&contents, &config.db_ty)
+        .into_iter()
+        .map(|query| {
+            let sql_fn = format_ident!("{}_sql", query.name);
+            let params_const = format_ident!("{}_PARAMS", query.name.to_screaming_snake_case());
+            let sql = &query.sql;
+            let params = &query.params;
+            let doc = if query.doc.is_empty() {
+                format!("The `{}` query loaded from `{}`.", query.name, path)
+            } else {
+                query.doc.join("\n")
+            };
+
+            quote! {
+                #[doc = #doc]
+                pub fn #sql_fn() -> &'static str {
+                    #sql
+                }
+
+                #[doc = concat!("Bind parameter names, in order, for [`Self::", stringify!(#sql_fn), "`].")]
+                pub const #params_const: &'static [&'static str] = &[#(#params),*];
+            }
+        });
+
+    quote! {
+        #[automatically_derived]
+        impl #ident {
+            // Ties this file into Cargo's dependency tracking so edits trigger a
+            // recompile, even though the derive already parsed it above.
+            #[doc(hidden)]
+            const QUERIES_FILE: &'static str = include_str!(#full_path_str);
+
+            #(#accessors)*
         }
     }
 }
@@ -266,8 +1132,23 @@ struct Config<'a> {
     db_ty: DbType,
     model_schema_ident: Ident,
     table_name: String,
+    /// Span of the `#[table(...)]` argument, or the struct ident when the
+    /// table name is left at its default, for diagnostics that need to point
+    /// at "whatever decided the table name".
+    table_name_span: proc_macro2::Span,
     id_column_ident: Ident,
     external_id: bool,
+    foreign_keys: Vec<ForeignKey>,
+    queries_file: Option<String>,
+    cache: Option<CacheConfig>,
+}
+
+/// Parsed `#[cache]` / `#[cache(capacity = N)]` struct attribute.
+struct CacheConfig {
+    /// Once this many rows are cached, further misses are looked up but not
+    /// inserted, until entries are removed by [`clear_cache`] or by the
+    /// cached `update`/`delete` paths. `None` means unbounded.
+    capacity: Option<usize>,
 }
 
 impl<'a> Config<'a> {
@@ -304,6 +1185,7 @@ impl<'a> Config<'a> {
 
         let external_id = attrs.iter().any(|a| a.path().is_ident("external_id"));
 
+        let mut table_name_span = None;
         let table_name = attrs
             .iter()
             .find(|a| a.path().is_ident("table"))
@@ -311,6 +1193,7 @@ impl<'a> Config<'a> {
                 let mut table = None;
                 attr.parse_nested_meta(|meta| {
                     if let Some(ident) = meta.path.get_ident() {
+                        table_name_span = Some(ident.span());
                         table = Some(ident.to_string());
                     }
                     Ok(())
@@ -319,6 +1202,32 @@ impl<'a> Config<'a> {
                 table
             })
             .unwrap_or_else(|| ident.to_string().to_table_case());
+        let table_name_span = table_name_span.unwrap_or_else(|| ident.span());
+
+        let foreign_keys = ForeignKey::parse_all(named);
+
+        let queries_file = attrs
+            .iter()
+            .find(|a| a.path().is_ident("queries"))
+            .map(|attr| {
+                attr.parse_args::<LitStr>()
+                    .expect("#[queries(\"path/to/file.sql\")] expects a string literal")
+                    .value()
+            });
+
+        let cache = attrs.iter().find(|a| a.path().is_ident("cache")).map(|attr| {
+            let mut capacity = None;
+            if matches!(attr.meta, syn::Meta::List(_)) {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("capacity") {
+                        capacity = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+                    }
+                    Ok(())
+                })
+                .expect("valid #[cache(capacity = N)] attribute");
+            }
+            CacheConfig { capacity }
+        });
 
         Self {
             ident,
@@ -327,8 +1236,12 @@ impl<'a> Config<'a> {
             db_ty,
             model_schema_ident,
             table_name,
+            table_name_span,
             id_column_ident,
             external_id,
+            foreign_keys,
+            queries_file,
+            cache,
         }
     }
 
@@ -396,3 +1309,212 @@ impl DbType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_for(src: &str) -> Config<'static> {
+        let DeriveInput { ident, data, attrs, .. } =
+            syn::parse_str(src).expect("valid struct");
+        let Data::Struct(DataStruct {
+            fields: Fields::Named(FieldsNamed { named, .. }),
+            ..
+        }) = data
+        else {
+            panic!("expected a struct with named fields");
+        };
+        // Leaked so the borrowed `Config<'a>` can outlive this helper; only
+        // ever called a handful of times from tests.
+        let ident = Box::leak(Box::new(ident));
+        let named = Box::leak(Box::new(named));
+        let attrs = Box::leak(Box::new(attrs));
+        Config::new(attrs, ident, named)
+    }
+
+    #[test]
+    fn rewrite_named_placeholders_passes_through_type_casts() {
+        let (sql, params) = rewrite_named_placeholders(
+            "created_at > :since::timestamp AND name = :name",
+            &DbType::Postgres,
+        );
+        assert_eq!(sql, "created_at > $1::timestamp AND name = $2");
+        assert_eq!(params, vec!["since".to_string(), "name".to_string()]);
+    }
+
+    #[test]
+    fn rewrite_named_placeholders_basic() {
+        let (sql, params) = rewrite_named_placeholders("name = :name", &DbType::Sqlite);
+        assert_eq!(sql, "name = ?");
+        assert_eq!(params, vec!["name".to_string()]);
+    }
+
+    #[test]
+    fn parse_named_queries_splits_on_name_marker() {
+        let queries = parse_named_queries(
+            "-- name: find_active\n-- Returns active users.\nSELECT * FROM users WHERE active = :active\n",
+            &DbType::Postgres,
+        );
+        assert_eq!(queries.len(), 1);
+        assert_eq!(queries[0].name, "find_active");
+        assert_eq!(queries[0].doc, vec!["Returns active users.".to_string()]);
+        assert_eq!(queries[0].sql, "SELECT * FROM users WHERE active = $1");
+        assert_eq!(queries[0].params, vec!["active".to_string()]);
+    }
+
+    #[test]
+    fn column_sql_type_unwraps_option_as_nullable() {
+        let ty: syn::Type = syn::parse_str("Option<i32>").unwrap();
+        let (sql_type, nullable) = column_sql_type(&ty, &DbType::Postgres);
+        assert_eq!(sql_type, "INTEGER");
+        assert!(nullable);
+
+        let ty: syn::Type = syn::parse_str("String").unwrap();
+        let (sql_type, nullable) = column_sql_type(&ty, &DbType::MySql);
+        assert_eq!(sql_type, "VARCHAR(255)");
+        assert!(!nullable);
+    }
+
+    #[test]
+    #[cfg(feature = "validate-sql")]
+    fn find_candidate_span_matches_whole_words_only() {
+        let candidates = vec![
+            ("id".to_string(), proc_macro2::Span::call_site()),
+            ("users".to_string(), proc_macro2::Span::call_site()),
+        ];
+        // "identifier" contains the substring "id" but isn't the `id` token.
+        assert!(find_candidate_span("Expected: identifier, found: EOF", &candidates).is_none());
+        assert!(find_candidate_span("Expected: identifier, found: id", &candidates).is_some());
+        assert!(find_candidate_span("table \"users\" not recognized", &candidates).is_some());
+    }
+
+    #[test]
+    fn build_create_table_sql_any_backend_has_no_autoincrement_clause() {
+        let config = config_for("#[database(Any)] struct User { id: i32, name: String }");
+        let sql = build_create_table_sql(&config, r#""users""#);
+        assert!(!sql.contains("AUTOINCREMENT"));
+        assert!(sql.contains(r#"PRIMARY KEY ("id")"#));
+    }
+
+    #[test]
+    fn build_create_table_sql_sqlite_inlines_autoincrement_pk() {
+        let config = config_for("struct User { id: i32, name: String }");
+        let sql = build_create_table_sql(&config, r#""users""#);
+        assert!(sql.contains(r#""id" INTEGER PRIMARY KEY AUTOINCREMENT"#));
+        // The inline PK means no separate `PRIMARY KEY (...)` clause is added.
+        assert!(!sql.contains("PRIMARY KEY (\"id\")"));
+    }
+
+    #[test]
+    fn build_create_table_sql_postgres_uses_identity_and_nullable_option() {
+        let config = config_for(
+            "#[database(Postgres)] struct User { id: i32, name: String, nickname: Option<String> }",
+        );
+        let sql = build_create_table_sql(&config, r#""users""#);
+        assert!(sql.contains(r#""id" INTEGER GENERATED ALWAYS AS IDENTITY"#));
+        assert!(sql.contains(r#"PRIMARY KEY ("id")"#));
+        assert!(sql.contains(r#""name" TEXT NOT NULL"#));
+        assert!(sql.contains(r#""nickname" TEXT"#));
+        assert!(!sql.contains(r#""nickname" TEXT NOT NULL"#));
+    }
+
+    #[test]
+    fn build_create_table_sql_mysql_uses_auto_increment() {
+        let config = config_for("#[database(MySql)] struct User { id: i32, name: String }");
+        let sql = build_create_table_sql(&config, "`users`");
+        assert!(sql.contains("`id` INT AUTO_INCREMENT"));
+    }
+
+    #[test]
+    fn build_create_table_sql_external_id_has_no_autoincrement_clause() {
+        let config =
+            config_for("#[external_id] struct User { id: i32, name: String }");
+        let sql = build_create_table_sql(&config, r#""users""#);
+        assert!(!sql.contains("AUTOINCREMENT"));
+        assert!(sql.contains(r#"PRIMARY KEY ("id")"#));
+    }
+
+    #[test]
+    fn foreign_key_sql_joins_and_fetches_on_parent_column() {
+        let (select_with_rel_sql, fetch_rel_sql) = foreign_key_sql(
+            r#"SELECT "posts"."id" FROM "posts""#,
+            r#""posts""#,
+            r#""author_handle""#,
+            r#""users""#,
+            r#""handle""#,
+        );
+        assert_eq!(
+            select_with_rel_sql,
+            r#"SELECT "posts"."id" FROM "posts" JOIN "users" ON "posts"."author_handle" = "users"."handle""#
+        );
+        // Regression: this must filter on the FK's own `parent_column`
+        // ("handle"), not on the parent's id column.
+        assert_eq!(fetch_rel_sql, r#"SELECT * FROM "users" WHERE "handle" = $1"#);
+    }
+
+    #[test]
+    fn foreign_key_parse_all_honors_column_and_table_overrides() {
+        let config = config_for(concat!(
+            "struct Post { id: i32, ",
+            "#[foreign_key(User, column = \"handle\", table = \"people\")] author_id: i32 }",
+        ));
+        let fks = &config.foreign_keys;
+        assert_eq!(fks.len(), 1);
+        assert_eq!(fks[0].parent_column, "handle");
+        assert_eq!(fks[0].parent_table.as_deref(), Some("people"));
+        assert_eq!(fks[0].rel_name, "author");
+    }
+
+    #[test]
+    fn foreign_key_parse_all_defaults_column_to_id_and_table_to_none() {
+        let config =
+            config_for("struct Post { id: i32, #[foreign_key(User)] author_id: i32 }");
+        let fks = &config.foreign_keys;
+        assert_eq!(fks.len(), 1);
+        assert_eq!(fks[0].parent_column, "id");
+        assert_eq!(fks[0].parent_table, None);
+    }
+
+    #[test]
+    fn build_query_builder_impl_wires_schema_table_and_columns() {
+        let config = config_for("#[database(Postgres)] struct User { id: i32, name: String }");
+        let tokens = build_query_builder_impl(&config).to_string();
+        assert!(tokens.contains("fn query"));
+        assert!(tokens.contains("QueryBuilder"));
+        assert!(tokens.contains("sqlx :: Postgres"));
+        assert!(tokens.contains("table_name"));
+        assert!(tokens.contains("columns"));
+    }
+
+    #[test]
+    fn build_cache_support_reads_through_and_invalidates_by_id_update_delete() {
+        let config = config_for("#[cache] struct User { id: i32, name: String }");
+        let cache = config.cache.as_ref().expect("#[cache] parsed");
+        let id_ty: syn::Type = syn::parse_str("i32").unwrap();
+        let db_ty = quote! { ::sqlx::Sqlite };
+        let support = build_cache_support(&config, cache, &id_ty, &db_ty);
+
+        let static_and_clear = support.static_and_clear.to_string();
+        assert!(static_and_clear.contains("USER_ID_CACHE"));
+        assert!(static_and_clear.contains("fn clear_cache"));
+
+        let overrides = support.trait_method_overrides.to_string();
+        assert!(overrides.contains("async fn by_id"));
+        assert!(overrides.contains("async fn update"));
+        assert!(overrides.contains("async fn delete"));
+        // Unbounded cache: the capacity check always passes.
+        assert!(overrides.contains("if true"));
+    }
+
+    #[test]
+    fn build_cache_support_honors_capacity_limit() {
+        let config = config_for("#[cache(capacity = 2)] struct User { id: i32, name: String }");
+        let cache = config.cache.as_ref().expect("#[cache] parsed");
+        let id_ty: syn::Type = syn::parse_str("i32").unwrap();
+        let db_ty = quote! { ::sqlx::Sqlite };
+        let support = build_cache_support(&config, cache, &id_ty, &db_ty);
+
+        let overrides = support.trait_method_overrides.to_string();
+        assert!(overrides.contains("cache . len () < 2"));
+    }
+}